@@ -1,20 +1,44 @@
-use crate::rope::{NodeRef, RopeNode, delete, insert, report};
+use crate::rope::{NodeRef, RopeNode, char_to_line, delete, index_at, insert, line_to_char, report};
 use core::str;
 use std::str::Chars;
+use std::time::Instant;
 use std::{char, fs};
 use std::{
     io::{self, Write},
     usize,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindDirection {
+    Forward,
+    Backward,
+}
+
 pub struct Editor {
     pub rope: NodeRef,
     pub filename: Option<String>,
     pub cursor_index: usize,
     pub cursor_row: usize,
     pub cursor_col: usize,
+    pub row_offset: usize,
+    pub col_offset: usize,
+    // The column horizontal movement last settled on; vertical movement
+    // clamps to it per-line but never overwrites it, so it "sticks" across
+    // shorter lines the way most editors remember your column.
+    pub desired_col: usize,
     pub history: Vec<NodeRef>,
     pub future: Vec<NodeRef>,
+    // Current incremental-search match, highlighted by `render` while set.
+    pub search_match: Option<(usize, usize)>,
+    // How many columns a '\t' advances to, kilo-style.
+    pub tab_stop: usize,
+    // Number of edits since the last save; drives the quit guard and the
+    // status bar's modified marker.
+    pub dirty: usize,
+    // Transient message shown by `render` in place of the help text.
+    pub status_message: Option<(String, Instant)>,
+    // Whether `render` draws a line-number gutter before each line.
+    pub show_line_numbers: bool,
 }
 
 impl Editor {
@@ -25,8 +49,16 @@ impl Editor {
             cursor_index: 0,
             cursor_row: 0,
             cursor_col: 0,
+            row_offset: 0,
+            col_offset: 0,
+            desired_col: 0,
             history: Vec::new(),
             future: Vec::new(),
+            search_match: None,
+            tab_stop: 4,
+            dirty: 0,
+            status_message: None,
+            show_line_numbers: true,
         }
     }
 
@@ -38,15 +70,20 @@ impl Editor {
         self.cursor_index = 0;
         self.cursor_row = 0;
         self.cursor_col = 0;
+        self.desired_col = 0;
         self.update_cursor_position();
         self.filename = Some(filename.to_string());
+        self.dirty = 0;
+        self.set_status(format!("Opened {}", filename));
         Ok(())
     }
 
-    pub fn save(&self) -> io::Result<()> {
+    pub fn save(&mut self) -> io::Result<()> {
         if let Some(name) = &self.filename {
             let text = report(&self.rope);
-            fs::write(name, text)?;
+            fs::write(name, &text)?;
+            self.dirty = 0;
+            self.set_status(format!("Saved {} bytes to {}", text.len(), name));
         }
         Ok(())
     }
@@ -63,7 +100,9 @@ impl Editor {
         let processed = unescape(text);
         self.rope = insert(self.rope.clone(), self.cursor_index, &processed);
         self.cursor_index += processed.chars().count();
+        self.dirty += 1;
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     pub fn delete_at_cursor(&mut self, count: usize) {
@@ -72,7 +111,9 @@ impl Editor {
         let start = (self.cursor_index - count).min(self.length());
         self.rope = delete(self.rope.clone(), start, end);
         self.cursor_index = start.min(self.length());
+        self.dirty += 1;
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     // CURSOR MOVEMENT
@@ -81,6 +122,7 @@ impl Editor {
         if self.cursor_index > 0 {
             self.cursor_index -= 1;
             self.update_cursor_position();
+            self.desired_col = self.cursor_col;
         }
     }
 
@@ -89,95 +131,57 @@ impl Editor {
         if self.cursor_index < len {
             self.cursor_index += 1;
             self.update_cursor_position();
+            self.desired_col = self.cursor_col;
         }
     }
 
     pub fn move_cursor_start(&mut self) {
         self.cursor_index = 0;
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     pub fn move_cursor_end(&mut self) {
         self.cursor_index = self.length();
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     pub fn move_cursor_up(&mut self) {
-        if self.cursor_index == 0 {
+        if self.cursor_row == 0 {
             return;
         }
 
-        let text = report(&self.rope);
-        let lines: Vec<&str> = text.lines().collect();
-
         let target_row = self.cursor_row - 1;
-        let target_col = self.cursor_col.min(lines[target_row].chars().count());
-
-        let new_index: usize = lines
-            .iter()
-            .take(target_row)
-            .map(|l| l.chars().count() + 1)
-            .sum::<usize>()
-            + target_col;
-
-        self.cursor_index = new_index.min(self.length());
+        let target_col = self.desired_col.min(self.line_len(target_row));
+        self.cursor_index = (self.line_start(target_row) + target_col).min(self.length());
         self.update_cursor_position();
     }
 
     pub fn move_cursor_down(&mut self) {
-        let text = report(&self.rope);
-        let lines: Vec<&str> = text.lines().collect();
-        if self.cursor_row + 1 >= lines.len() {
+        if self.cursor_row + 1 >= self.line_count() {
             return;
         }
 
         let target_row = self.cursor_row + 1;
-        let target_col = self.cursor_col.min(lines[target_row].chars().count());
-
-        let new_index: usize = lines
-            .iter()
-            .take(target_row)
-            .map(|l| l.chars().count() + 1)
-            .sum::<usize>()
-            + target_col;
-
-        self.cursor_index = new_index.min(self.length());
+        let target_col = self.desired_col.min(self.line_len(target_row));
+        self.cursor_index = (self.line_start(target_row) + target_col).min(self.length());
         self.update_cursor_position();
     }
 
     // START / END PER LINE
 
     pub fn move_to_line_end(&mut self) {
-        let text = report(&self.rope);
-        let lines: Vec<&str> = text.lines().collect();
-        if self.cursor_row >= lines.len() {
-            return;
-        }
-        let new_index: usize = lines
-            .iter()
-            .take(self.cursor_row + 1)
-            .map(|l| l.chars().count() + 1)
-            .sum::<usize>()
-            - 1;
-        self.cursor_index = new_index.min(self.length());
+        let row = self.cursor_row;
+        self.cursor_index = (self.line_start(row) + self.line_len(row)).min(self.length());
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     pub fn move_to_line_start(&mut self) {
-        let text = report(&self.rope);
-        let mut index = 0;
-        let mut row = 0;
-        for ch in text.chars() {
-            if row == self.cursor_row {
-                break;
-            }
-            index += 1;
-            if ch == '\n' {
-                row += 1;
-            }
-        }
-        self.cursor_index = index;
+        self.cursor_index = self.line_start(self.cursor_row);
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     // WORD LEVEL MOVEMENT
@@ -194,6 +198,7 @@ impl Editor {
         }
         self.cursor_index = i.min(chars.len());
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     pub fn move_word_left(&mut self) {
@@ -211,6 +216,36 @@ impl Editor {
         }
         self.cursor_index = i;
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
+    }
+
+    // SEARCH
+
+    /// Scans the flattened buffer for `query`, starting one char past `start`
+    /// in `direction` (after it going forward, before it going backward) and
+    /// wrapping around the buffer's end, returning the char index of the first hit.
+    pub fn find(&self, query: &str, start: usize, direction: FindDirection) -> Option<usize> {
+        if query.is_empty() {
+            return None;
+        }
+
+        let text: Vec<char> = report(&self.rope).chars().collect();
+        let pattern: Vec<char> = query.chars().collect();
+        let len = text.len();
+        if len == 0 || pattern.len() > len {
+            return None;
+        }
+
+        for offset in 1..=len {
+            let i = match direction {
+                FindDirection::Forward => (start + offset) % len,
+                FindDirection::Backward => (start + len - offset) % len,
+            };
+            if i + pattern.len() <= len && text[i..i + pattern.len()] == pattern[..] {
+                return Some(i);
+            }
+        }
+        None
     }
 
     // LINE INSERTION AND DELETION
@@ -232,28 +267,18 @@ impl Editor {
 
     pub fn delete_current_line(&mut self) {
         self.save_history();
-        let text = report(&self.rope);
-        let lines: Vec<&str> = text.lines().collect();
-        if lines.is_empty() {
+        if self.length() == 0 {
             return;
         }
 
-        let start: usize = lines
-            .iter()
-            .take(self.cursor_row)
-            .map(|l| l.chars().count() + 1)
-            .sum::<usize>();
-
-        let end: usize = start
-            + lines[self.cursor_row].chars().count()
-            + if self.cursor_row + 1 < lines.len() {
-                1
-            } else {
-                0
-            };
+        let row = self.cursor_row;
+        let start = self.line_start(row);
+        let end = start + self.line_len(row) + if row + 1 < self.line_count() { 1 } else { 0 };
         self.rope = delete(self.rope.clone(), start, end);
         self.cursor_index = start.min(self.length());
+        self.dirty += 1;
         self.update_cursor_position();
+        self.desired_col = self.cursor_col;
     }
 
     // UNDO & REDO OPERATIONS
@@ -280,6 +305,10 @@ impl Editor {
 
     // UTILITIES
 
+    pub fn set_status(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+    }
+
     pub fn save_history(&mut self) {
         if self.history.len() > 1000 {
             self.history.remove(0);
@@ -289,27 +318,73 @@ impl Editor {
     }
 
     pub fn update_cursor_position(&mut self) {
-        let text = report(&self.rope);
-        let mut row = 0;
-        let mut col = 0;
-        let mut chars_seen = 0;
+        let (row, col) = char_to_line(&self.rope, self.cursor_index);
+        self.cursor_row = row;
+        self.cursor_col = col;
+    }
 
-        for ch in text.chars() {
-            if chars_seen == self.cursor_index {
-                break;
-            }
+    // LINE LOOKUPS (O(height), backed by the rope's cached newline counts)
+
+    pub fn line_count(&self) -> usize {
+        let newlines = match &self.rope {
+            Some(n) => n.borrow().newline_count(),
+            None => 0,
+        };
+        newlines + 1
+    }
+
+    pub fn line_start(&self, row: usize) -> usize {
+        line_to_char(&self.rope, row)
+    }
+
+    pub fn line_len(&self, row: usize) -> usize {
+        let start = self.line_start(row);
+        let end = if row + 1 < self.line_count() {
+            self.line_start(row + 1) - 1
+        } else {
+            self.length()
+        };
+        end - start
+    }
+
+    /// Columns needed to right-align every line number, i.e.
+    /// `floor(log10(line_count)) + 1`; zero while the gutter is toggled off.
+    pub fn gutter_width(&self) -> usize {
+        if !self.show_line_numbers {
+            return 0;
+        }
+        self.line_count().to_string().len()
+    }
+
+    // O(line length × height), unlike flattening the whole rope: walks
+    // only the chars of this one line, one `index_at` descent per char.
+    fn line_text(&self, row: usize) -> String {
+        let start = self.line_start(row);
+        let len = self.line_len(row);
+        (start..start + len)
+            .filter_map(|i| index_at(&self.rope, i))
+            .collect()
+    }
 
-            if ch == '\n' {
-                row += 1;
-                col = 0;
+    // TABS / RENDER COLUMN
+
+    /// Maps a char column to its on-screen column, expanding `\t` out to the
+    /// next `tab_stop` multiple the way kilo's `render_x` does.
+    pub fn render_col(&self, row: usize, col: usize) -> usize {
+        let line = self.line_text(row);
+        let mut rcol = 0;
+        for ch in line.chars().take(col) {
+            if ch == '\t' {
+                rcol += self.tab_stop - (rcol % self.tab_stop);
             } else {
-                col += 1;
+                rcol += 1;
             }
-            chars_seen += 1;
         }
+        rcol
+    }
 
-        self.cursor_row = row;
-        self.cursor_col = col;
+    pub fn cursor_render_col(&self) -> usize {
+        self.render_col(self.cursor_row, self.cursor_col)
     }
 
     pub fn display(&self) {
@@ -336,6 +411,26 @@ impl Editor {
         );
     }
 
+    // VIEWPORT
+
+    pub fn scroll(&mut self, screen_rows: usize, screen_cols: usize) {
+        if self.cursor_row < self.row_offset {
+            self.row_offset = self.cursor_row;
+        }
+        if screen_rows > 0 && self.cursor_row >= self.row_offset + screen_rows {
+            self.row_offset = self.cursor_row - screen_rows + 1;
+        }
+
+        // col_offset lives in render-column space so tabs scroll correctly.
+        let render_col = self.cursor_render_col();
+        if render_col < self.col_offset {
+            self.col_offset = render_col;
+        }
+        if screen_cols > 0 && render_col >= self.col_offset + screen_cols {
+            self.col_offset = render_col - screen_cols + 1;
+        }
+    }
+
     pub fn length(&self) -> usize {
         if let Some(node) = &self.rope {
             return node.borrow().length();
@@ -344,6 +439,21 @@ impl Editor {
     }
 }
 
+/// Expands `\t` in an already-fetched line into spaces up to `tab_stop`,
+/// so `render` can draw it without tabs throwing off column alignment.
+pub fn expand_line(line: &str, tab_stop: usize) -> Vec<char> {
+    let mut out = Vec::new();
+    for ch in line.chars() {
+        if ch == '\t' {
+            let spaces = tab_stop - (out.len() % tab_stop);
+            out.extend(std::iter::repeat(' ').take(spaces));
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
 fn unescape(text: &str) -> String {
     text.replace("\\n", "\n")
         .replace("\\t", "\t")
@@ -7,6 +7,15 @@ pub type NodeRef = Option<Rc<RefCell<RopeNode>>>;
 #[derive(Debug, Clone)]
 pub struct RopeNode {
     weight: usize,
+    // Cached over the whole subtree so length/line lookups are O(height)
+    // instead of re-scanning the text on every call.
+    len: usize,
+    newlines: usize,
+    // Number of chars in the subtree's last (possibly unterminated) line,
+    // i.e. chars after its last '\n' (or the whole subtree if it has none).
+    // Needed by `char_to_line` to account for a line that straddles the
+    // boundary between this subtree and whatever follows it.
+    tail_col: usize,
     data: Option<String>,
     left: NodeRef,
     right: NodeRef,
@@ -14,8 +23,17 @@ pub struct RopeNode {
 
 impl RopeNode {
     pub fn new_leaf(data: &str) -> NodeRef {
+        let len = data.chars().count();
+        let newlines = data.matches('\n').count();
+        let tail_col = match data.rfind('\n') {
+            Some(i) => data[i + 1..].chars().count(),
+            None => data.chars().count(),
+        };
         Some(Rc::new(RefCell::new(RopeNode {
-            weight: data.len(),
+            weight: len,
+            len,
+            newlines,
+            tail_col,
             data: Some(data.to_string()),
             left: None,
             right: None,
@@ -23,12 +41,32 @@ impl RopeNode {
     }
 
     pub fn new_internal(left: NodeRef, right: NodeRef) -> NodeRef {
-        let weight = match &left {
-            Some(l) => l.borrow().length(),
-            None => 0,
+        let (left_len, left_newlines, left_tail) = match &left {
+            Some(l) => {
+                let lb = l.borrow();
+                (lb.len, lb.newlines, lb.tail_col)
+            }
+            None => (0, 0, 0),
+        };
+        let (right_len, right_newlines, right_tail) = match &right {
+            Some(r) => {
+                let rb = r.borrow();
+                (rb.len, rb.newlines, rb.tail_col)
+            }
+            None => (0, 0, 0),
+        };
+        // If the right side has no newline of its own, it's a continuation
+        // of the left side's last line, so the tail spans both.
+        let tail_col = if right_newlines > 0 {
+            right_tail
+        } else {
+            left_tail + right_tail
         };
         Some(Rc::new(RefCell::new(RopeNode {
-            weight,
+            weight: left_len,
+            len: left_len + right_len,
+            newlines: left_newlines + right_newlines,
+            tail_col,
             data: None,
             left,
             right,
@@ -36,12 +74,15 @@ impl RopeNode {
     }
 
     pub fn length(&self) -> usize {
-        if self.left.is_none() && self.right.is_none() {
-            return self.data.as_ref().unwrap().len();
-        }
-        let left_len = self.left.as_ref().map(|l| l.borrow().length()).unwrap_or(0);
-        let right_len = self.right.as_ref().map(|l| l.borrow().length()).unwrap_or(0);
-        left_len + right_len
+        self.len
+    }
+
+    pub fn newline_count(&self) -> usize {
+        self.newlines
+    }
+
+    pub fn tail_col(&self) -> usize {
+        self.tail_col
     }
 }
 
@@ -107,6 +148,75 @@ pub fn delete(root: NodeRef, start: usize, end: usize) -> NodeRef {
     concatenate(left, right)
 }
 
+/// Descends the tree using the cached `len`/`newlines` totals to turn a char
+/// index into a `(row, col)` pair without flattening the rope.
+pub fn char_to_line(node: &NodeRef, index: usize) -> (usize, usize) {
+    if let Some(n) = node {
+        let nb = n.borrow();
+        if nb.left.is_none() && nb.right.is_none() {
+            let data = nb.data.as_ref().unwrap();
+            let mut row = 0;
+            let mut col = 0;
+            for ch in data.chars().take(index) {
+                if ch == '\n' {
+                    row += 1;
+                    col = 0;
+                } else {
+                    col += 1;
+                }
+            }
+            return (row, col);
+        }
+
+        if index < nb.weight {
+            return char_to_line(&nb.left, index);
+        }
+
+        let left_newlines = nb.left.as_ref().map(|l| l.borrow().newlines).unwrap_or(0);
+        let left_tail_col = nb.left.as_ref().map(|l| l.borrow().tail_col).unwrap_or(0);
+        let (row, col) = char_to_line(&nb.right, index - nb.weight);
+        // If the right subtree hasn't hit a newline yet (row == 0), the
+        // matched position is still on the left subtree's last line, so its
+        // column continues from where that line left off.
+        if row == 0 {
+            return (left_newlines, left_tail_col + col);
+        }
+        return (row + left_newlines, col);
+    }
+    (0, 0)
+}
+
+/// Inverse of `char_to_line`: the char index of the first character of `row`,
+/// found by recursing into whichever subtree's cached newline count covers it.
+pub fn line_to_char(node: &NodeRef, row: usize) -> usize {
+    if let Some(n) = node {
+        let nb = n.borrow();
+        if nb.left.is_none() && nb.right.is_none() {
+            if row == 0 {
+                return 0;
+            }
+            let data = nb.data.as_ref().unwrap();
+            let mut seen = 0;
+            for (i, ch) in data.chars().enumerate() {
+                if ch == '\n' {
+                    seen += 1;
+                    if seen == row {
+                        return i + 1;
+                    }
+                }
+            }
+            return data.chars().count();
+        }
+
+        let left_newlines = nb.left.as_ref().map(|l| l.borrow().newlines).unwrap_or(0);
+        if row <= left_newlines {
+            return line_to_char(&nb.left, row);
+        }
+        return nb.weight + line_to_char(&nb.right, row - left_newlines);
+    }
+    0
+}
+
 pub fn report(node: &NodeRef) -> String {
     if let Some(n) = node {
         let nb = n.borrow();
@@ -117,3 +227,41 @@ pub fn report(node: &NodeRef) -> String {
     }
     String::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn char_to_line_after_incremental_inserts() {
+        // Mirrors what the TUI does per keystroke: each char is its own
+        // split+concatenate, so the rope ends up with many leaves and the
+        // "hello world" line straddles several node boundaries.
+        let mut root = RopeNode::new_leaf("");
+        for (i, ch) in "hello world".chars().enumerate() {
+            root = insert(root, i, &ch.to_string());
+        }
+
+        assert_eq!(report(&root), "hello world");
+        assert_eq!(char_to_line(&root, 11), (0, 11));
+
+        for i in 0..=11 {
+            assert_eq!(char_to_line(&root, i), (0, i));
+        }
+    }
+
+    #[test]
+    fn char_to_line_across_multiple_lines() {
+        let mut root = RopeNode::new_leaf("");
+        for (i, ch) in "foo\nbar\nbaz".chars().enumerate() {
+            root = insert(root, i, &ch.to_string());
+        }
+
+        assert_eq!(char_to_line(&root, 0), (0, 0));
+        assert_eq!(char_to_line(&root, 3), (0, 3));
+        assert_eq!(char_to_line(&root, 4), (1, 0));
+        assert_eq!(char_to_line(&root, 7), (1, 3));
+        assert_eq!(char_to_line(&root, 8), (2, 0));
+        assert_eq!(char_to_line(&root, 11), (2, 3));
+    }
+}
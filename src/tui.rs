@@ -7,23 +7,42 @@ use crossterm::{
     terminal::{self, Clear, ClearType},
 };
 
-use crate::editor::Editor;
+use crate::editor::{Editor, FindDirection, expand_line};
+
+// How many consecutive Ctrl+Q presses it takes to quit with unsaved changes.
+const QUIT_TIMES: u8 = 3;
+// How long a status message stays in the status bar before it's cleared.
+const STATUS_MESSAGE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 pub fn start_tui(editor: &mut Editor) -> std::io::Result<()> {
     terminal::enable_raw_mode()?;
 
     let mut stdout = stdout();
     execute!(stdout, Hide)?;
+    let mut quit_presses: u8 = 0;
 
     loop {
         if event::poll(std::time::Duration::from_millis(50))? {
             if let Event::Key(key_event) = event::read()? {
+                let is_quit_key = matches!(key_event.code, KeyCode::Char('q'))
+                    && key_event.modifiers == KeyModifiers::CONTROL;
+                if !is_quit_key {
+                    quit_presses = 0;
+                }
+
                 match key_event.code {
                     // EXIT
                     KeyCode::Char('q') if key_event.modifiers == KeyModifiers::CONTROL => {
-                        terminal::disable_raw_mode()?;
-                        execute!(stdout, Show)?;
-                        return Ok(());
+                        quit_presses += 1;
+                        if editor.dirty == 0 || quit_presses >= QUIT_TIMES {
+                            terminal::disable_raw_mode()?;
+                            execute!(stdout, Show)?;
+                            return Ok(());
+                        }
+                        editor.set_status(format!(
+                            "Unsaved changes! Press Ctrl+Q {} more time(s) to quit.",
+                            QUIT_TIMES - quit_presses
+                        ));
                     }
 
                     // SAVE
@@ -39,11 +58,29 @@ pub fn start_tui(editor: &mut Editor) -> std::io::Result<()> {
                     KeyCode::Char('o') if key_event.modifiers == KeyModifiers::CONTROL => {
                         let name = prompt(&mut stdout, "Open file")?;
                         if let Err(e) = editor.open_file(&name) {
-                            let msg = format!("Failed to open {}: {}", name, e);
-                            prompt(&mut stdout, &msg)?;
+                            editor.set_status(format!("Failed to open {}: {}", name, e));
                         }
                     }
 
+                    // WORD MOVEMENT (must come before the CHARACTER INPUT catch-all below,
+                    // which otherwise matches every Char key first, Ctrl-modified or not)
+                    KeyCode::Char('b') if key_event.modifiers == KeyModifiers::CONTROL => {
+                        editor.move_word_left()
+                    }
+                    KeyCode::Right if key_event.modifiers == KeyModifiers::CONTROL => {
+                        editor.move_word_right()
+                    }
+
+                    // FIND (must also come before the CHARACTER INPUT catch-all below)
+                    KeyCode::Char('f') if key_event.modifiers == KeyModifiers::CONTROL => {
+                        find_mode(editor, &mut stdout)?;
+                    }
+
+                    // LINE NUMBERS (must also come before the CHARACTER INPUT catch-all below)
+                    KeyCode::Char('l') if key_event.modifiers == KeyModifiers::CONTROL => {
+                        editor.show_line_numbers = !editor.show_line_numbers;
+                    }
+
                     // CHARACTER INPUT
                     KeyCode::Char(c) => {
                         if key_event.modifiers == KeyModifiers::CONTROL {
@@ -57,9 +94,10 @@ pub fn start_tui(editor: &mut Editor) -> std::io::Result<()> {
                         }
                     }
 
-                    // ENTER / BACKSPACE
+                    // ENTER / BACKSPACE / TAB
                     KeyCode::Enter => editor.insert_at_cursor("\n"),
                     KeyCode::Backspace => editor.delete_at_cursor(1),
+                    KeyCode::Tab => editor.insert_at_cursor("\t"),
 
                     // ARROWS
                     KeyCode::Left => editor.move_cursor_left(),
@@ -71,77 +109,218 @@ pub fn start_tui(editor: &mut Editor) -> std::io::Result<()> {
                     KeyCode::Home => editor.move_to_line_start(),
                     KeyCode::End => editor.move_to_line_end(),
 
-                    // WORD MOVEMENT
-                    KeyCode::Char('b') if key_event.modifiers == KeyModifiers::CONTROL => {
-                        editor.move_word_left()
+                    _ => {}
+                }
+            }
+        }
+        render(editor, &mut stdout)?;
+    }
+}
+
+/// Ctrl+F incremental search, modeled on kilo's find mode: every keystroke
+/// re-scans the buffer and moves the cursor to the next hit, Up/Down (or
+/// Ctrl+P/Ctrl+N) step to the previous/next match, Enter confirms in place,
+/// and Esc restores the cursor to where the search started.
+fn find_mode(editor: &mut Editor, stdout: &mut Stdout) -> std::io::Result<()> {
+    let saved_index = editor.cursor_index;
+    let mut query = String::new();
+    let mut anchor = saved_index;
+
+    loop {
+        render(editor, stdout)?;
+        draw_find_prompt(stdout, &query)?;
+
+        if event::poll(std::time::Duration::from_millis(50))? {
+            if let Event::Key(key_event) = event::read()? {
+                match key_event.code {
+                    KeyCode::Esc => {
+                        editor.cursor_index = saved_index;
+                        editor.update_cursor_position();
+                        editor.search_match = None;
+                        return Ok(());
                     }
-                    KeyCode::Char('f') if key_event.modifiers == KeyModifiers::CONTROL => {
-                        editor.move_word_right()
+
+                    KeyCode::Enter => {
+                        editor.search_match = None;
+                        return Ok(());
+                    }
+
+                    KeyCode::Backspace => {
+                        query.pop();
+                        anchor = saved_index;
+                        search_step(editor, &query, anchor, FindDirection::Forward, true);
+                    }
+
+                    KeyCode::Up => search_step(editor, &query, anchor, FindDirection::Backward, false),
+                    KeyCode::Down => search_step(editor, &query, anchor, FindDirection::Forward, false),
+                    KeyCode::Char('p') if key_event.modifiers == KeyModifiers::CONTROL => {
+                        search_step(editor, &query, anchor, FindDirection::Backward, false)
+                    }
+                    KeyCode::Char('n') if key_event.modifiers == KeyModifiers::CONTROL => {
+                        search_step(editor, &query, anchor, FindDirection::Forward, false)
+                    }
+
+                    KeyCode::Char(c) if key_event.modifiers != KeyModifiers::CONTROL => {
+                        query.push(c);
+                        search_step(editor, &query, anchor, FindDirection::Forward, true);
                     }
 
                     _ => {}
                 }
+
+                if let Some((start, _)) = editor.search_match {
+                    anchor = start;
+                }
             }
         }
-        render(editor, &mut stdout)?;
     }
 }
 
-fn render(editor: &Editor, stdout: &mut Stdout) -> std::io::Result<()> {
+/// Runs one search step and updates the cursor/highlight from the result.
+/// `inclusive` lets a freshly typed query re-match right at `anchor` instead
+/// of requiring a strictly later occurrence.
+fn search_step(
+    editor: &mut Editor,
+    query: &str,
+    anchor: usize,
+    direction: FindDirection,
+    inclusive: bool,
+) {
+    let len = crate::rope::report(&editor.rope).chars().count();
+    if len == 0 {
+        editor.search_match = None;
+        return;
+    }
+
+    let start = if inclusive {
+        match direction {
+            FindDirection::Forward => (anchor + len - 1) % len,
+            FindDirection::Backward => (anchor + 1) % len,
+        }
+    } else {
+        anchor
+    };
+
+    match editor.find(query, start, direction) {
+        Some(index) => {
+            editor.cursor_index = index;
+            editor.update_cursor_position();
+            editor.search_match = Some((index, index + query.chars().count()));
+        }
+        None => editor.search_match = None,
+    }
+}
+
+fn draw_find_prompt(stdout: &mut Stdout, query: &str) -> std::io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    execute!(stdout, MoveTo(0, rows - 1))?;
+    let status = format!("Find: {} | Enter confirm | Esc cancel", query);
+    write!(
+        stdout,
+        "\x1b[7m{:<width$}\x1b[0m",
+        status,
+        width = cols as usize
+    )?;
+    stdout.flush()
+}
+
+fn render(editor: &mut Editor, stdout: &mut Stdout) -> std::io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    let screen_cols = cols as usize;
+    let screen_rows = (rows as usize).saturating_sub(1); // last row is the status bar
+
+    // Gutter eats into the text area, so the viewport must scroll against
+    // what's left over, not the full terminal width.
+    let gutter_width = editor.gutter_width();
+    let text_offset = if gutter_width > 0 { gutter_width + 1 } else { 0 };
+    let text_cols = screen_cols.saturating_sub(text_offset);
+    editor.scroll(screen_rows, text_cols);
+
     execute!(stdout, Clear(ClearType::All))?;
-    execute!(stdout, MoveTo(0, 0))?;
 
     let text = crate::rope::report(&editor.rope);
-    let mut row = 0;
-    let mut col = 0;
-
-    for (i, ch) in text.chars().enumerate() {
-        if ch == '\n' {
-            row += 1;
-            col = 0;
-            writeln!(stdout)?;
-            execute!(stdout, MoveTo(0, row as u16))?;
+    let lines: Vec<&str> = text.split('\n').collect();
+    let cursor_render_col = editor.cursor_render_col();
+
+    // A search match only ever highlights within a single rendered line.
+    let match_highlight = editor.search_match.map(|(start, end)| {
+        let (row, col) = crate::rope::char_to_line(&editor.rope, start);
+        let render_col = editor.render_col(row, col);
+        (row, render_col, render_col + (end - start))
+    });
+
+    for screen_row in 0..screen_rows {
+        execute!(stdout, MoveTo(0, screen_row as u16))?;
+
+        let file_row = editor.row_offset + screen_row;
+        let Some(line) = lines.get(file_row) else {
             continue;
-        }
+        };
 
-        if i == editor.cursor_index {
-            write!(stdout, "\x1b[7m{}\x1b[0m", ch)?;
-        } else {
-            write!(stdout, "{}", ch)?;
+        if gutter_width > 0 {
+            write!(
+                stdout,
+                "\x1b[2m{:>width$}\x1b[0m ",
+                file_row + 1,
+                width = gutter_width
+            )?;
         }
 
-        col += 1;
-    }
+        let rendered = expand_line(line, editor.tab_stop);
+
+        for screen_col in 0..text_cols {
+            let render_col = editor.col_offset + screen_col;
+            let on_cursor = file_row == editor.cursor_row && render_col == cursor_render_col;
+            let on_match = match_highlight
+                .map(|(row, start, end)| file_row == row && (start..end).contains(&render_col))
+                .unwrap_or(false);
 
-    if editor.cursor_index == text.len() {
-        execute!(
-            stdout,
-            MoveTo(editor.cursor_col as u16, editor.cursor_row as u16)
-        )?;
-        write!(stdout, "\x1b[7m \x1b[0m")?;
+            match rendered.get(render_col) {
+                Some(ch) if on_cursor => write!(stdout, "\x1b[7m{}\x1b[0m", ch)?,
+                Some(ch) if on_match => write!(stdout, "\x1b[43;30m{}\x1b[0m", ch)?,
+                Some(ch) => write!(stdout, "{}", ch)?,
+                None if on_cursor => write!(stdout, "\x1b[7m \x1b[0m")?,
+                None => break,
+            }
+        }
     }
 
+    let cursor_screen_row = editor.cursor_row.saturating_sub(editor.row_offset);
+    let cursor_screen_col =
+        text_offset + cursor_render_col.saturating_sub(editor.col_offset);
     execute!(
         stdout,
-        MoveTo(editor.cursor_col as u16, editor.cursor_row as u16)
+        MoveTo(cursor_screen_col as u16, cursor_screen_row as u16)
     )?;
 
-    let (cols, rows) = terminal::size()?;
     execute!(stdout, MoveTo(0, rows - 1))?;
 
+    if let Some((_, timestamp)) = &editor.status_message {
+        if timestamp.elapsed() >= STATUS_MESSAGE_TIMEOUT {
+            editor.status_message = None;
+        }
+    }
+
     let filename = editor.filename.clone().unwrap_or("[No Name]".into());
+    let dirty_marker = if editor.dirty > 0 { " *" } else { "" };
+    let trailing = match &editor.status_message {
+        Some((message, _)) => message.clone(),
+        None => "Ctrl+A Save | Ctrl+O Open | Ctrl+F Find | Ctrl+L Line#s | Ctrl+Q Quit".to_string(),
+    };
     let status = format!(
-        "{} | Ln {}, Col {} | Ctrl+A Save | Ctrl+O Open | Ctrl+Q Quit",
+        "{}{} | Ln {}, Col {} | {}",
         filename,
+        dirty_marker,
         editor.cursor_row + 1,
-        editor.cursor_col + 1,
+        cursor_render_col + 1,
+        trailing,
     );
 
     write!(
         stdout,
         "\x1b[7m{:<width$}\x1b[0m",
         status,
-        width = cols as usize
+        width = screen_cols
     )?;
 
     stdout.flush()?;